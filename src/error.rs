@@ -25,4 +25,7 @@ pub(crate) enum Error {
 
     #[error("Not enough parts in request line: {0}")]
     NotEnoughParts(String),
+
+    #[error("Server cannot satisfy the requested range (status {0})")]
+    RangeNotSatisfiable(u16),
 }
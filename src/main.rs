@@ -1,22 +1,24 @@
+use std::io::{Read, Write};
 use std::str::FromStr;
 
 use anyhow::{Context, Result};
 use chrono::{SecondsFormat, Utc};
 use clap::ArgAction;
 
-use crate::error::Error::{ResponseBody, SendRequest};
+use crate::error::Error::{RangeNotSatisfiable, SendRequest};
 
 mod error;
 mod form;
 mod model;
 mod parse;
+mod serve;
 
 fn main() -> Result<()> {
     let cmd = clap::Command::new("httper")
         .arg(
             clap::Arg::new("file")
                 .help("File containing the HTTP request")
-                .required(true),
+                .required(false),
         )
         .arg(
             clap::Arg::new("verbose")
@@ -31,12 +33,54 @@ fn main() -> Result<()> {
                 .long("output")
                 .value_name("FILE")
                 .help("Output file for the response"),
+        )
+        .arg(
+            clap::Arg::new("resume")
+                .action(ArgAction::SetTrue)
+                .long("resume")
+                .help("Resume a partial download with a Range request when the output file exists"),
+        )
+        .subcommand(
+            clap::Command::new("serve")
+                .about("Serve a directory over HTTP to run requests against")
+                .arg(
+                    clap::Arg::new("dir")
+                        .help("Directory to serve")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("port")
+                        .short('p')
+                        .long("port")
+                        .value_name("PORT")
+                        .default_value("8080")
+                        .help("Port to listen on"),
+                )
+                .arg(
+                    clap::Arg::new("bind")
+                        .short('b')
+                        .long("bind")
+                        .value_name("ADDR")
+                        .default_value("127.0.0.1")
+                        .help("Address to bind to"),
+                ),
         );
 
     let matches = cmd.get_matches();
-    let filepath = matches.get_one::<String>("file").unwrap();
+
+    if let Some(serve) = matches.subcommand_matches("serve") {
+        let dir = serve.get_one::<String>("dir").unwrap();
+        let port = serve.get_one::<String>("port").unwrap();
+        let bind = serve.get_one::<String>("bind").unwrap();
+        return serve::run(dir, bind, port);
+    }
+
+    let filepath = matches
+        .get_one::<String>("file")
+        .context("a request file is required")?;
     let output = matches.get_one::<String>("output");
     let verbose = matches.get_flag("verbose");
+    let resume = matches.get_flag("resume");
 
     let content =
         std::fs::read_to_string(filepath).context(format!("cannot open file at: {}", filepath))?;
@@ -53,18 +97,37 @@ fn main() -> Result<()> {
     let requests = parse::parse_requests(content.as_str(), client.clone(), directory)?;
 
     for request in requests {
-        send_one(request, &client, output, verbose).unwrap();
+        send_one(request, &client, output, verbose, resume).unwrap();
     }
 
     Ok(())
 }
 
 fn send_one(
-    request: reqwest::blocking::Request,
+    mut request: reqwest::blocking::Request,
     client: &reqwest::blocking::Client,
     output: Option<&String>,
     verbose: bool,
+    resume: bool,
 ) -> Result<()> {
+    // When resuming, pick up from the size of an already-present output file by
+    // asking the server for the remaining byte range.
+    let resume_offset = resume
+        .then(|| output.map(std::path::Path::new))
+        .flatten()
+        .filter(|path| path.exists())
+        .and_then(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .filter(|&len| len > 0);
+
+    if let Some(offset) = resume_offset {
+        request.headers_mut().insert(
+            reqwest::header::RANGE,
+            reqwest::header::HeaderValue::from_str(&format!("bytes={}-", offset))
+                .expect("range header value is always valid"),
+        );
+    }
+
     if verbose {
         println!("\n{:?}", request);
         let body = request.body();
@@ -75,14 +138,13 @@ fn send_one(
     }
 
     let start = std::time::Instant::now();
-    let response = client.execute(request).map_err(SendRequest)?;
+    let mut response = client.execute(request).map_err(SendRequest)?;
 
     let duration = start.elapsed();
 
     let headers = response.headers().clone();
     let status_code = response.status();
-    let content_length = response.content_length();
-    let bytes = response.bytes().map_err(ResponseBody)?;
+    let declared_length = response.content_length();
 
     let content_type = headers
         .iter()
@@ -104,49 +166,142 @@ fn send_one(
 
             mime::Mime::from_str(header_value).ok()
         })
-        .collect::<Vec<_>>();
-
-    // todo consider disposition header here maybe?
+        .next();
 
-    if let Some(content_type) = content_type.first() {
-        let extensions = mime_guess::get_mime_extensions(content_type);
+    let disposition = filename_from_disposition(&headers);
 
-        if extensions.is_some() {
-            let extension = extensions.unwrap().first().unwrap();
+    if resume_offset.is_some() && status_code == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        return Err(RangeNotSatisfiable(status_code.as_u16()).into());
+    }
 
-            if verbose {
-                println!("Content type: {:?}", content_type);
-                println!("Extension: {:?}", extension);
+    // `206 Partial Content` lets us append to the existing file, but only once we
+    // confirm the returned range actually starts where we asked; otherwise (a
+    // plain `200 OK`, or a 206 starting elsewhere) we overwrite from scratch.
+    let append = match resume_offset {
+        Some(offset) if status_code == reqwest::StatusCode::PARTIAL_CONTENT => {
+            match content_range_start(&headers) {
+                Some(start) if start == offset => true,
+                Some(start) => {
+                    eprintln!(
+                        "Warning: 206 Content-Range starts at {} not {}; overwriting",
+                        start, offset
+                    );
+                    false
+                }
+                None => {
+                    eprintln!(
+                        "Warning: 206 response without a parseable Content-Range; overwriting"
+                    );
+                    false
+                }
             }
+        }
+        _ => false,
+    };
 
-            let filename = if let Some(output) = output {
-                output.to_string()
-            } else {
-                format!(
-                    "response-{}.{}",
-                    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
-                    extension
-                )
-            };
+    // Content-Encoding may chain several codecs, listed in the order they were
+    // applied (outermost last); we decode them in reverse so the body comes out
+    // as the server originally produced it.
+    let encodings: Vec<String> = headers
+        .get(reqwest::header::CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(',')
+                .map(|token| token.trim().to_ascii_lowercase())
+                .filter(|token| !token.is_empty() && token != "identity")
+                .collect()
+        })
+        .unwrap_or_default();
 
-            if let Err(e) = std::fs::write(filename, bytes.clone()) {
-                eprintln!("Failed to write response to file: {}", e);
-            }
-        }
-    }
+    // Decide where to write the body. An explicit `-o` or the server's
+    // Content-Disposition wins regardless of content type; only the synthesized
+    // fallback name needs a known extension to pick a suffix.
+    let extension = content_type
+        .as_ref()
+        .and_then(mime_guess::get_mime_extensions)
+        .and_then(|extensions| extensions.first().copied());
 
-    let content_length = content_length.unwrap_or(bytes.len() as u64);
+    let filename = if let Some(output) = output {
+        Some(output.to_string())
+    } else if let Some(disposition) = &disposition {
+        Some(disposition.clone())
+    } else {
+        extension.map(|extension| {
+            format!(
+                "response-{}.{}",
+                Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                extension
+            )
+        })
+    };
+
+    // A textual body is buffered only so it can be printed under `--verbose`;
+    // everything else streams straight to disk.
+    let is_textual = content_type.as_ref().map(is_textual_type).unwrap_or(false);
 
     if verbose {
-        println!("Headers: {:?}", headers);
-        if let Some(content_type) = content_type.first() {
-            if !content_type.to_string().starts_with("image") {
-                println!("Content: {}", String::from_utf8_lossy(&bytes));
-            }
+        // The reported length reflects the decoded body, so drop the now-stale
+        // Content-Encoding from the echoed headers.
+        let mut shown = headers.clone();
+        if !encodings.is_empty() {
+            shown.remove(reqwest::header::CONTENT_ENCODING);
+        }
+        println!("Headers: {:?}", shown);
+        if let Some(content_type) = &content_type {
             println!("Content type: {:?}", content_type);
         }
+        if let Some(extension) = extension {
+            println!("Extension: {:?}", extension);
+        }
     }
 
+    let content_length = if verbose && is_textual {
+        // Buffer only so the body can be printed, then persist it too if we have
+        // somewhere to write — don't silently drop the file in the verbose case.
+        let mut reader = decoding_reader(&mut response, &encodings)?;
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .context("reading response body")?;
+        println!("Content: {}", String::from_utf8_lossy(&bytes));
+        drop(reader);
+
+        if let Some(filename) = &filename {
+            if let Err(e) = stream_to_file(&mut &bytes[..], filename, append) {
+                eprintln!("Failed to write response to file: {}", e);
+            }
+        }
+
+        if append {
+            resume_offset.unwrap_or(0) + bytes.len() as u64
+        } else {
+            bytes.len() as u64
+        }
+    } else if let Some(filename) = &filename {
+        let mut reader = decoding_reader(&mut response, &encodings)?;
+        match stream_to_file(&mut *reader, filename, append) {
+            Ok(written) => {
+                if append {
+                    resume_offset.unwrap_or(0) + written
+                } else {
+                    written
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to write response to file: {}", e);
+                declared_length.unwrap_or(0)
+            }
+        }
+    } else if !encodings.is_empty() {
+        // Nowhere to write, but decode anyway so the reported length is the
+        // decoded size rather than the encoded Content-Length.
+        let mut reader = decoding_reader(&mut response, &encodings)?;
+        std::io::copy(&mut reader, &mut std::io::sink()).context("reading response body")?
+    } else {
+        declared_length.unwrap_or(0)
+    };
+
     println!(
         "\nResponse code: {}; Time: {}ms ({:?}); Content length: {} bytes ({:.2} MB)",
         status_code,
@@ -158,3 +313,216 @@ fn send_one(
 
     Ok(())
 }
+
+/// Stream a response body into `filename`, returning the number of bytes written.
+///
+/// Copies through a 64 KiB `BufWriter` with `std::io::copy` so arbitrarily large
+/// downloads are never held in memory in their entirety. When `append` is set the
+/// body is written to the end of an existing file (for resumed range requests)
+/// instead of truncating it.
+fn stream_to_file(reader: &mut dyn Read, filename: &str, append: bool) -> Result<u64> {
+    const BUFFER_SIZE: usize = 64 * 1024;
+
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(filename)
+        .with_context(|| format!("cannot create output file: {}", filename))?;
+    let mut writer = std::io::BufWriter::with_capacity(BUFFER_SIZE, file);
+    let written = std::io::copy(reader, &mut writer)?;
+    writer.flush()?;
+
+    Ok(written)
+}
+
+/// Whether a response of this type is worth echoing as text under `--verbose`.
+///
+/// Covers `text/*` plus the common textual `application/*` payloads an HTTP CLI
+/// is usually pointed at — JSON, XML, JavaScript, and their `+json`/`+xml`
+/// structured-syntax suffixes.
+fn is_textual_type(content_type: &mime::Mime) -> bool {
+    if content_type.type_() == mime::TEXT {
+        return true;
+    }
+
+    if content_type.type_() == mime::APPLICATION {
+        let subtype = content_type.subtype();
+        return subtype == mime::JSON
+            || subtype == mime::XML
+            || subtype == mime::JAVASCRIPT
+            || subtype.as_str().ends_with("+json")
+            || subtype.as_str().ends_with("+xml");
+    }
+
+    false
+}
+
+/// Parse the starting byte offset out of a `Content-Range: bytes <start>-<end>/<total>` header.
+fn content_range_start(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    let value = headers
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?;
+    let range = value.trim().strip_prefix("bytes")?.trim_start();
+    range.split('-').next()?.trim().parse().ok()
+}
+
+/// Wrap a response body in the decoders named by its `Content-Encoding` chain.
+///
+/// Encodings are applied in reverse of their header order (outermost first), so
+/// a `gzip, br` body is run through the brotli decoder and then the gzip one.
+/// Unknown codecs are passed through verbatim with a warning.
+fn decoding_reader<'a>(
+    source: &'a mut dyn Read,
+    encodings: &[String],
+) -> Result<Box<dyn Read + 'a>> {
+    let mut reader: Box<dyn Read + 'a> = Box::new(source);
+
+    for encoding in encodings.iter().rev() {
+        reader = match encoding.as_str() {
+            "gzip" | "x-gzip" => Box::new(flate2::read::GzDecoder::new(reader)),
+            "deflate" => Box::new(flate2::read::ZlibDecoder::new(reader)),
+            "br" => Box::new(brotli::Decompressor::new(reader, 64 * 1024)),
+            "zstd" => Box::new(
+                zstd::stream::read::Decoder::new(reader).context("initialising zstd decoder")?,
+            ),
+            other => {
+                eprintln!("Unsupported Content-Encoding '{}'; writing verbatim", other);
+                reader
+            }
+        };
+    }
+
+    Ok(reader)
+}
+
+/// Pick a download filename from the response's `Content-Disposition` header.
+///
+/// Parses per RFC 6266: the value is split on `;`, the first token being the
+/// disposition type and the rest `name=value` parameters. The extended
+/// `filename*` parameter (`charset'lang'pct-encoded`) is preferred and
+/// percent-decoded, falling back to the quoted `filename=` value. The result is
+/// reduced to its final path component and any `..` rejected, so a hostile
+/// server cannot escape the output directory.
+fn filename_from_disposition(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let value = headers
+        .get(reqwest::header::CONTENT_DISPOSITION)?
+        .to_str()
+        .ok()?;
+
+    let mut plain = None;
+    let mut extended = None;
+    for param in value.split(';').skip(1) {
+        let Some((key, raw)) = param.split_once('=') else {
+            continue;
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename" => plain = Some(raw.trim().trim_matches('"').to_string()),
+            "filename*" => extended = decode_ext_value(raw.trim()),
+            _ => {}
+        }
+    }
+
+    sanitize_filename(extended.or(plain)?.as_str())
+}
+
+/// Decode an RFC 5987 extended value of the form `charset'lang'pct-encoded`.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _lang = parts.next()?;
+    let encoded = parts.next()?;
+
+    let bytes = percent_decode(encoded);
+    match charset.to_ascii_uppercase().as_str() {
+        // ISO-8859-1 maps each byte directly onto the matching code point.
+        "ISO-8859-1" => Some(bytes.iter().map(|&b| b as char).collect()),
+        _ => Some(String::from_utf8_lossy(&bytes).into_owned()),
+    }
+}
+
+/// Percent-decode a string into raw bytes, leaving malformed escapes untouched.
+pub(crate) fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex_digit(bytes[i + 1]), hex_digit(bytes[i + 2])) {
+                out.push((hi << 4) | lo);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Reduce a server-supplied name to a safe single path component.
+fn sanitize_filename(name: &str) -> Option<String> {
+    std::path::Path::new(name.trim())
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_strips_directories_and_rejects_traversal() {
+        assert_eq!(sanitize_filename("report.pdf").as_deref(), Some("report.pdf"));
+        assert_eq!(sanitize_filename("/etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(sanitize_filename("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(sanitize_filename(".."), None);
+        assert_eq!(sanitize_filename(""), None);
+    }
+
+    #[test]
+    fn decodes_extended_filename_parameter() {
+        assert_eq!(
+            decode_ext_value("UTF-8''%e2%82%ac.pdf").as_deref(),
+            Some("\u{20ac}.pdf")
+        );
+        assert_eq!(
+            decode_ext_value("ISO-8859-1''%A3.txt").as_deref(),
+            Some("\u{a3}.txt")
+        );
+    }
+
+    #[test]
+    fn percent_decode_leaves_malformed_escapes() {
+        assert_eq!(percent_decode("a%20b"), b"a b");
+        assert_eq!(percent_decode("100%"), b"100%");
+    }
+
+    #[test]
+    fn disposition_prefers_extended_and_sanitizes() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_DISPOSITION,
+            "attachment; filename=\"../evil.sh\"; filename*=UTF-8''%e2%82%ac.pdf"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            filename_from_disposition(&headers).as_deref(),
+            Some("\u{20ac}.pdf")
+        );
+    }
+}
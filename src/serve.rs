@@ -0,0 +1,201 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Serve `dir` over HTTP on `bind:port`, blocking until the process is killed.
+///
+/// This is a deliberately small single-threaded server meant for firing `.http`
+/// request files against local fixtures, not a production host.
+pub(crate) fn run(dir: &str, bind: &str, port: &str) -> Result<()> {
+    let root =
+        std::fs::canonicalize(dir).with_context(|| format!("cannot serve directory: {}", dir))?;
+    let address = format!("{}:{}", bind, port);
+
+    let listener =
+        TcpListener::bind(&address).with_context(|| format!("cannot bind to {}", address))?;
+
+    println!("Serving {} on http://{}", root.display(), address);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle(stream, &root) {
+                    eprintln!("Error handling connection: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Connection failed: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, root: &Path) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let target = match request_line.split_whitespace().nth(1) {
+        Some(target) => target,
+        None => return respond(&mut stream, 400, "Bad Request", "text/plain; charset=utf-8", b""),
+    };
+
+    // Drop any query string and percent-decode the remaining path.
+    let raw_path = target.split('?').next().unwrap_or("/");
+    let request_path = String::from_utf8_lossy(&crate::percent_decode(raw_path)).into_owned();
+
+    let resolved = match resolve(root, &request_path) {
+        Some(resolved) => resolved,
+        None => return respond(&mut stream, 403, "Forbidden", "text/plain; charset=utf-8", b""),
+    };
+
+    // Lexical `..` rejection is not enough: a symlink inside the served tree could
+    // still point outside it, so re-check containment against the canonical path.
+    if let Ok(canonical) = resolved.canonicalize() {
+        if !canonical.starts_with(root) {
+            return respond(&mut stream, 403, "Forbidden", "text/plain; charset=utf-8", b"");
+        }
+    }
+
+    if resolved.is_dir() {
+        let index = resolved.join("index.html");
+        if index.is_file() {
+            return serve_file(&mut stream, &index);
+        }
+
+        let body = directory_index(&request_path, &resolved)?;
+        return respond(&mut stream, 200, "OK", "text/html; charset=utf-8", body.as_bytes());
+    }
+
+    if resolved.is_file() {
+        serve_file(&mut stream, &resolved)
+    } else {
+        respond(&mut stream, 404, "Not Found", "text/plain; charset=utf-8", b"")
+    }
+}
+
+/// Map a request path onto a path under `root`, rejecting any `..` traversal.
+fn resolve(root: &Path, request_path: &str) -> Option<PathBuf> {
+    let mut resolved = root.to_path_buf();
+    for component in Path::new(request_path).components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(resolved)
+}
+
+fn serve_file(stream: &mut TcpStream, path: &Path) -> Result<()> {
+    let body = std::fs::read(path)?;
+    let content_type = mime_guess::from_path(path).first_or_octet_stream();
+    respond(stream, 200, "OK", content_type.as_ref(), &body)
+}
+
+/// Render a minimal HTML directory listing with escaped, linkable entry names.
+fn directory_index(request_path: &str, dir: &Path) -> Result<String> {
+    let mut entries = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let title = html_escape(request_path);
+    let base = request_path.trim_end_matches('/');
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">");
+    html.push_str(&format!("<title>Index of {}</title></head><body>", title));
+    html.push_str(&format!("<h1>Index of {}</h1><ul>", title));
+    for entry in entries {
+        // The href needs URL-encoding (spaces, `#`, `?`, `%`, …); the visible
+        // text keeps HTML-escaping.
+        let href = format!("{}/{}", base, percent_encode(&entry));
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>",
+            html_escape(&href),
+            html_escape(&entry)
+        ));
+    }
+    html.push_str("</ul></body></html>");
+
+    Ok(html)
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: u16,
+    reason: &str,
+    content_type: &str,
+    body: &[u8],
+) -> Result<()> {
+    let header = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        content_type,
+        body.len(),
+    );
+
+    stream.write_all(header.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+/// Percent-encode a single path segment, leaving only the RFC 3986 unreserved set.
+fn percent_encode(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    out
+}
+
+fn html_escape(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_rejects_parent_traversal() {
+        let root = Path::new("/srv/www");
+        assert!(resolve(root, "/../etc/passwd").is_none());
+        assert!(resolve(root, "/a/../../etc").is_none());
+    }
+
+    #[test]
+    fn resolve_maps_normal_paths_under_root() {
+        let root = Path::new("/srv/www");
+        assert_eq!(
+            resolve(root, "/index.html"),
+            Some(PathBuf::from("/srv/www/index.html"))
+        );
+        assert_eq!(resolve(root, "/"), Some(PathBuf::from("/srv/www")));
+    }
+
+    #[test]
+    fn percent_encode_escapes_unsafe_characters() {
+        assert_eq!(percent_encode("a b#c?d%e"), "a%20b%23c%3Fd%25e");
+        assert_eq!(percent_encode("plain-name_1.txt"), "plain-name_1.txt");
+    }
+}